@@ -7,16 +7,39 @@ use heed::RoTxn;
 use super::FieldsIdsMap;
 use crate::attribute_patterns::PatternMatch;
 use crate::{
-    is_faceted_by, FieldId, FilterableAttributesFeatures, FilterableAttributesRule, Index,
-    LocalizedAttributesRule, Result,
+    is_faceted_by, AscDesc, Criterion, FieldId, FilterableAttributesFeatures,
+    FilterableAttributesRule, Index, LocalizedAttributesRule, Member, OrderBy, Result,
 };
 
+/// The relative priority of a searchable attribute, used by the attribute ranking rule.
+///
+/// A field matched earlier in the `searchableAttributes` list gets a higher weight than one
+/// matched later, so the first declared attribute outranks the last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Weight(u16);
+
+impl Weight {
+    fn new(weight: usize) -> Self {
+        Self(weight.try_into().unwrap_or(u16::MAX))
+    }
+
+    /// Returns the weight as a plain integer, higher meaning more important.
+    pub fn level(&self) -> u16 {
+        self.0
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Metadata {
     pub searchable: bool,
     pub sortable: bool,
+    displayed: bool,
+    weight: Option<Weight>,
+    default_facet_values_order: OrderBy,
+    facet_values_order_rule_id: Option<NonZeroU16>,
     localized_attributes_rule_id: Option<NonZeroU16>,
     filterable_attributes_rule_id: Option<NonZeroU16>,
+    ranking_rule_id: Option<NonZeroU16>,
 }
 
 #[derive(Debug, Clone)]
@@ -98,6 +121,34 @@ impl FieldIdMapWithMetadata {
     pub fn metadata_builder(&self) -> &MetadataBuilder {
         &self.builder
     }
+
+    /// Iterate over the searchable fields and their attribute-ranking weight, in ids order.
+    pub fn iter_searchable_with_weights(
+        &self,
+    ) -> impl Iterator<Item = (FieldId, &str, Weight)> + '_ {
+        self.iter()
+            .filter_map(|(id, name, metadata)| metadata.weight().map(|weight| (id, name, weight)))
+    }
+
+    /// Returns the names of the displayed facet-searchable fields, safe to surface in error
+    /// messages (e.g. `InvalidFacetSearchFacetName`'s suggestion list).
+    pub fn displayed_facet_searchable_names<'a>(
+        &'a self,
+        rules: &'a [FilterableAttributesRule],
+    ) -> impl Iterator<Item = &'a str> {
+        self.iter().filter_map(move |(_, name, metadata)| {
+            (metadata.is_displayed()
+                && metadata.filterable_attributes_features(rules).is_facet_searchable())
+            .then_some(name)
+        })
+    }
+
+    /// Returns the names of the displayed sortable fields, safe to surface in error messages.
+    pub fn displayed_sortable_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.iter().filter_map(|(_, name, metadata)| {
+            (metadata.is_displayed() && metadata.is_sortable()).then_some(name)
+        })
+    }
 }
 
 impl Metadata {
@@ -121,6 +172,17 @@ impl Metadata {
         Some(rule)
     }
 
+    /// Returns the `asc`/`desc` custom ranking criterion this field participates in, if any.
+    pub fn ranking_direction<'rules>(
+        &self,
+        rules: &'rules [AscDesc],
+    ) -> Option<&'rules AscDesc> {
+        let ranking_rule_id = self.ranking_rule_id?.get();
+        // - 1: `ranking_rule_id` is NonZero
+        let rule = rules.get((ranking_rule_id - 1) as usize).unwrap();
+        Some(rule)
+    }
+
     pub fn filterable_attributes_features(
         &self,
         rules: &[FilterableAttributesRule],
@@ -139,6 +201,28 @@ impl Metadata {
         self.searchable
     }
 
+    /// Returns `true` if the field is part of `displayedAttributes`, and thus safe to
+    /// surface, e.g. by name, in errors and other user-facing messages.
+    pub fn is_displayed(&self) -> bool {
+        self.displayed
+    }
+
+    /// Returns the ranking weight of this field in the attribute ranking rule, if searchable.
+    pub fn weight(&self) -> Option<Weight> {
+        self.weight
+    }
+
+    /// Returns the order in which this field's facet values should be returned, falling back
+    /// to the index's default (the `*` rule, or lexicographic) when no specific rule matches.
+    pub fn facet_values_order(&self, rules: &[(String, OrderBy)]) -> OrderBy {
+        let Some(facet_values_order_rule_id) = self.facet_values_order_rule_id else {
+            return self.default_facet_values_order;
+        };
+        // - 1: `facet_values_order_rule_id` is NonZero
+        let (_, order_by) = rules.get((facet_values_order_rule_id.get() - 1) as usize).unwrap();
+        *order_by
+    }
+
     /// Returns `true` if the field is part of the facet databases. (sortable, filterable, or facet searchable)
     pub fn is_faceted(&self, rules: &[FilterableAttributesRule]) -> bool {
         if self.is_sortable() {
@@ -164,6 +248,10 @@ pub struct MetadataBuilder {
     filterable_attributes: Vec<FilterableAttributesRule>,
     sortable_attributes: HashSet<String>,
     localized_attributes: Option<Vec<LocalizedAttributesRule>>,
+    facet_values_order: Vec<(String, OrderBy)>,
+    default_facet_values_order: OrderBy,
+    displayed_attributes: Option<Vec<String>>,
+    ranking_rules: Vec<AscDesc>,
 }
 
 impl MetadataBuilder {
@@ -177,11 +265,42 @@ impl MetadataBuilder {
         let sortable_attributes = index.sortable_fields(rtxn)?;
         let localized_attributes = index.localized_attributes_rules(rtxn)?;
 
+        let mut sort_facet_values_by = index.sort_facet_values_by(rtxn)?;
+        // the `*` entry is the fallback applied to every field that has no specific rule
+        let default_facet_values_order = sort_facet_values_by.remove("*").unwrap_or_default();
+        // `sort_facet_values_by` is a map and thus has no meaningful order of its own: sort its
+        // entries by pattern so the position -> id resolution below is deterministic, the same
+        // way `filterable_attributes`/`localized_attributes` resolve against an ordered `Vec`.
+        let mut facet_values_order: Vec<(String, OrderBy)> =
+            sort_facet_values_by.into_iter().collect();
+        facet_values_order.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let displayed_attributes = match index.displayed_fields(rtxn)? {
+            Some(fields) if fields.contains(&"*") => None,
+            None => None,
+            Some(fields) => Some(fields.into_iter().map(|s| s.to_string()).collect()),
+        };
+
+        // only the `asc`/`desc` custom ranking criteria are relevant to field metadata
+        let ranking_rules = index
+            .criteria(rtxn)?
+            .into_iter()
+            .filter_map(|criterion| match criterion {
+                Criterion::Asc(field) => Some(AscDesc::Asc(Member::Field(field))),
+                Criterion::Desc(field) => Some(AscDesc::Desc(Member::Field(field))),
+                _ => None,
+            })
+            .collect();
+
         Ok(Self {
             searchable_attributes,
             filterable_attributes,
             sortable_attributes,
             localized_attributes,
+            facet_values_order,
+            default_facet_values_order,
+            displayed_attributes,
+            ranking_rules,
         })
     }
 
@@ -206,15 +325,36 @@ impl MetadataBuilder {
     // }
 
     pub fn metadata_for_field(&self, field: &str) -> Metadata {
-        let searchable = match &self.searchable_attributes {
-            // A field is searchable if it is faceted by a searchable attribute
-            Some(attributes) => attributes.iter().any(|pattern| is_faceted_by(field, pattern)),
-            None => true,
+        let (searchable, weight) = match &self.searchable_attributes {
+            // A field is searchable if it is faceted by a searchable attribute, and its weight
+            // is derived from its position in the list: the earliest pattern wins the most weight.
+            Some(attributes) => {
+                let weight = attributes
+                    .iter()
+                    .position(|pattern| is_faceted_by(field, pattern))
+                    .map(|index| Weight::new(attributes.len() - index));
+                (weight.is_some(), weight)
+            }
+            // every field is searchable and shares the same default weight
+            None => (true, Some(Weight::new(0))),
         };
 
         // A field is sortable if it is faceted by a sortable attribute
         let sortable = self.sortable_attributes.iter().any(|pattern| is_faceted_by(field, pattern));
 
+        let facet_values_order_rule_id = self
+            .facet_values_order
+            .iter()
+            .position(|(pattern, _)| is_faceted_by(field, pattern))
+            // saturating_add(1): make `id` `NonZero`
+            .map(|id| NonZeroU16::new(id.saturating_add(1).try_into().unwrap()).unwrap());
+
+        let displayed = match &self.displayed_attributes {
+            // A field is displayed if it is faceted by a displayed attribute
+            Some(attributes) => attributes.iter().any(|pattern| is_faceted_by(field, pattern)),
+            None => true,
+        };
+
         let localized_attributes_rule_id = self
             .localized_attributes
             .iter()
@@ -230,11 +370,28 @@ impl MetadataBuilder {
             // saturating_add(1): make `id` `NonZero`
             .map(|id| NonZeroU16::new(id.saturating_add(1).try_into().unwrap()).unwrap());
 
+        let ranking_rule_id = self
+            .ranking_rules
+            .iter()
+            .position(|rule| match rule {
+                AscDesc::Asc(Member::Field(name)) | AscDesc::Desc(Member::Field(name)) => {
+                    name == field
+                }
+                AscDesc::Asc(Member::Geo(_)) | AscDesc::Desc(Member::Geo(_)) => false,
+            })
+            // saturating_add(1): make `id` `NonZero`
+            .map(|id| NonZeroU16::new(id.saturating_add(1).try_into().unwrap()).unwrap());
+
         Metadata {
             searchable,
             sortable,
+            displayed,
+            weight,
+            default_facet_values_order: self.default_facet_values_order,
+            facet_values_order_rule_id,
             localized_attributes_rule_id,
             filterable_attributes_rule_id,
+            ranking_rule_id,
         }
     }
 
@@ -253,4 +410,200 @@ impl MetadataBuilder {
     pub fn localized_attributes_rules(&self) -> Option<&[LocalizedAttributesRule]> {
         self.localized_attributes.as_deref()
     }
+
+    pub fn facet_values_order(&self) -> &[(String, OrderBy)] {
+        &self.facet_values_order
+    }
+
+    pub fn default_facet_values_order(&self) -> OrderBy {
+        self.default_facet_values_order
+    }
+
+    pub fn displayed_attributes(&self) -> Option<&[String]> {
+        self.displayed_attributes.as_deref()
+    }
+
+    pub fn ranking_rules(&self) -> &[AscDesc] {
+        &self.ranking_rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder_with(
+        searchable_attributes: Option<Vec<String>>,
+        facet_values_order: Vec<(String, OrderBy)>,
+        default_facet_values_order: OrderBy,
+        displayed_attributes: Option<Vec<String>>,
+        ranking_rules: Vec<AscDesc>,
+    ) -> MetadataBuilder {
+        MetadataBuilder {
+            searchable_attributes,
+            filterable_attributes: Vec::new(),
+            sortable_attributes: HashSet::from(["price".to_string()]),
+            localized_attributes: None,
+            facet_values_order,
+            default_facet_values_order,
+            displayed_attributes,
+            ranking_rules,
+        }
+    }
+
+    fn empty_builder() -> MetadataBuilder {
+        builder_with(None, Vec::new(), OrderBy::Lexicographic, None, Vec::new())
+    }
+
+    #[test]
+    fn weight_is_none_and_unsearchable_when_field_has_no_match() {
+        let builder = builder_with(
+            Some(vec!["title".to_string()]),
+            Vec::new(),
+            OrderBy::Lexicographic,
+            None,
+            Vec::new(),
+        );
+        let metadata = builder.metadata_for_field("overview");
+        assert!(!metadata.is_searchable());
+        assert_eq!(metadata.weight(), None);
+    }
+
+    #[test]
+    fn weight_favors_the_earliest_declared_pattern() {
+        let builder = builder_with(
+            Some(vec!["title".to_string(), "overview".to_string()]),
+            Vec::new(),
+            OrderBy::Lexicographic,
+            None,
+            Vec::new(),
+        );
+        let title = builder.metadata_for_field("title").weight().unwrap();
+        let overview = builder.metadata_for_field("overview").weight().unwrap();
+        assert!(title.level() > overview.level());
+    }
+
+    #[test]
+    fn weight_resolves_first_match_when_patterns_overlap() {
+        // two entries matching the same field: the earliest position wins
+        let builder = builder_with(
+            Some(vec!["title".to_string(), "title".to_string()]),
+            Vec::new(),
+            OrderBy::Lexicographic,
+            None,
+            Vec::new(),
+        );
+        let metadata = builder.metadata_for_field("title");
+        assert_eq!(metadata.weight().unwrap().level(), 2);
+    }
+
+    #[test]
+    fn every_field_is_searchable_with_the_same_weight_when_no_attributes_are_set() {
+        let builder = empty_builder();
+        let a = builder.metadata_for_field("a").weight().unwrap();
+        let b = builder.metadata_for_field("b").weight().unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn facet_values_order_falls_back_to_default_when_no_rule_matches() {
+        let builder = builder_with(None, Vec::new(), OrderBy::Count, None, Vec::new());
+        let metadata = builder.metadata_for_field("genre");
+        assert_eq!(metadata.facet_values_order(&[]), OrderBy::Count);
+    }
+
+    #[test]
+    fn facet_values_order_resolves_the_matching_rule() {
+        let rules = vec![("genre".to_string(), OrderBy::Count)];
+        let builder = builder_with(None, rules.clone(), OrderBy::Lexicographic, None, Vec::new());
+        let metadata = builder.metadata_for_field("genre");
+        assert_eq!(metadata.facet_values_order(&rules), OrderBy::Count);
+
+        // an unrelated field still falls back to the default
+        let other = builder.metadata_for_field("author");
+        assert_eq!(other.facet_values_order(&rules), OrderBy::Lexicographic);
+    }
+
+    #[test]
+    fn facet_values_order_resolves_first_match_when_patterns_overlap() {
+        let rules = vec![
+            ("genre".to_string(), OrderBy::Count),
+            ("genre".to_string(), OrderBy::Lexicographic),
+        ];
+        let builder = builder_with(None, rules.clone(), OrderBy::Lexicographic, None, Vec::new());
+        let metadata = builder.metadata_for_field("genre");
+        assert_eq!(metadata.facet_values_order(&rules), OrderBy::Count);
+    }
+
+    #[test]
+    fn ranking_direction_is_none_without_a_matching_rule() {
+        let builder = empty_builder();
+        let metadata = builder.metadata_for_field("price");
+        assert!(metadata.ranking_direction(&[]).is_none());
+    }
+
+    #[test]
+    fn ranking_direction_resolves_the_matching_asc_desc_rule() {
+        let rules = vec![AscDesc::Desc(Member::Field("price".to_string()))];
+        let builder = builder_with(None, Vec::new(), OrderBy::Lexicographic, None, rules.clone());
+        let metadata = builder.metadata_for_field("price");
+        match metadata.ranking_direction(&rules) {
+            Some(AscDesc::Desc(Member::Field(name))) => assert_eq!(name, "price"),
+            other => panic!("expected Desc(price), got {other:?}"),
+        }
+
+        let other = builder.metadata_for_field("title");
+        assert!(other.ranking_direction(&rules).is_none());
+    }
+
+    #[test]
+    fn ranking_direction_ignores_geo_members() {
+        let rules = vec![AscDesc::Asc(Member::Geo([0.0, 0.0]))];
+        let builder = builder_with(None, Vec::new(), OrderBy::Lexicographic, None, rules.clone());
+        let metadata = builder.metadata_for_field("_geo");
+        assert!(metadata.ranking_direction(&rules).is_none());
+    }
+
+    #[test]
+    fn is_displayed_true_when_no_displayed_attributes_are_set() {
+        let builder = empty_builder();
+        assert!(builder.metadata_for_field("any").is_displayed());
+    }
+
+    #[test]
+    fn is_displayed_false_for_fields_outside_displayed_attributes() {
+        let builder = builder_with(
+            None,
+            Vec::new(),
+            OrderBy::Lexicographic,
+            Some(vec!["title".to_string()]),
+            Vec::new(),
+        );
+        assert!(builder.metadata_for_field("title").is_displayed());
+        assert!(!builder.metadata_for_field("overview").is_displayed());
+    }
+
+    #[test]
+    fn displayed_names_hide_non_displayed_and_non_matching_fields() {
+        let builder = builder_with(
+            None,
+            Vec::new(),
+            OrderBy::Lexicographic,
+            Some(vec!["title".to_string(), "price".to_string()]),
+            Vec::new(),
+        );
+        let mut fields_ids_map = FieldsIdsMap::new();
+        fields_ids_map.insert("title").unwrap();
+        fields_ids_map.insert("price").unwrap();
+        fields_ids_map.insert("overview").unwrap();
+
+        let map = FieldIdMapWithMetadata::new(fields_ids_map, builder);
+
+        let sortable: Vec<&str> = map.displayed_sortable_names().collect();
+        assert_eq!(sortable, vec!["price"]);
+
+        // no filterable attributes rule is configured, so nothing is facet-searchable
+        let facet_searchable: Vec<&str> = map.displayed_facet_searchable_names(&[]).collect();
+        assert!(facet_searchable.is_empty());
+    }
 }
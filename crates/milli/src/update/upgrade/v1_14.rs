@@ -1,12 +1,81 @@
+use heed::types::{SerdeJson, Str};
 use heed::RwTxn;
 
 use super::UpgradeIndex;
-use crate::progress::Progress;
+use crate::progress::{Progress, VariableNameStep};
+use crate::vector::Embedder;
 use crate::{make_enum_progress, Index, Result};
 
 #[allow(non_camel_case_types)]
 pub(super) struct Latest_V1_13_To_Latest_V1_14();
 
+impl Latest_V1_13_To_Latest_V1_14 {
+    /// Key used to skip an embedder already migrated earlier in this same upgrade attempt.
+    ///
+    /// This checkpoint is written into the same `wtxn` as the migrated arroy data, and that
+    /// transaction is only committed once the whole upgrade chain across versions completes.
+    /// It therefore does **not** survive a process crash: the checkpoint and the migrated data
+    /// are committed, or rolled back, together. It only avoids redundant work if `upgrade` is
+    /// invoked more than once before that final commit (e.g. this step being retried).
+    fn checkpoint_key(embedder_name: &str) -> String {
+        format!("upgrade-v1_14-arroy-upgraded-{embedder_name}")
+    }
+
+    fn is_upgraded(wtxn: &mut RwTxn, index: &Index, embedder_name: &str) -> Result<bool> {
+        let key = Self::checkpoint_key(embedder_name);
+        Ok(index.main.remap_types::<Str, SerdeJson<bool>>().get(wtxn, &key)?.unwrap_or(false))
+    }
+
+    fn mark_upgraded(wtxn: &mut RwTxn, index: &Index, embedder_name: &str) -> Result<()> {
+        let key = Self::checkpoint_key(embedder_name);
+        index.main.remap_types::<Str, SerdeJson<bool>>().put(wtxn, &key, &true)?;
+        Ok(())
+    }
+
+    /// Upgrades a single embedder's arroy sub-store (scoped to its `embedder_id`), dispatching
+    /// to the arroy upgrade function matching its distance. `arroy::Distance` only has these
+    /// four variants, so the match below is exhaustive: there is no "unsupported distance" case.
+    fn upgrade_embedder(
+        rtxn: &heed::RoTxn,
+        wtxn: &mut RwTxn,
+        index: &Index,
+        embedder_id: u8,
+        distance: arroy::Distance,
+    ) -> Result<bool> {
+        let changed = match distance {
+            arroy::Distance::Cosine => arroy::upgrade::cosine_from_0_5_to_0_6(
+                rtxn,
+                index.vector_arroy,
+                wtxn,
+                index.vector_arroy,
+                embedder_id,
+            )?,
+            arroy::Distance::Euclidean => arroy::upgrade::euclidean_from_0_5_to_0_6(
+                rtxn,
+                index.vector_arroy,
+                wtxn,
+                index.vector_arroy,
+                embedder_id,
+            )?,
+            arroy::Distance::Manhattan => arroy::upgrade::manhattan_from_0_5_to_0_6(
+                rtxn,
+                index.vector_arroy,
+                wtxn,
+                index.vector_arroy,
+                embedder_id,
+            )?,
+            arroy::Distance::DotProduct => arroy::upgrade::dot_product_from_0_5_to_0_6(
+                rtxn,
+                index.vector_arroy,
+                wtxn,
+                index.vector_arroy,
+                embedder_id,
+            )?,
+        };
+        Ok(changed)
+    }
+}
+
 impl UpgradeIndex for Latest_V1_13_To_Latest_V1_14 {
     fn upgrade(
         &self,
@@ -17,21 +86,43 @@ impl UpgradeIndex for Latest_V1_13_To_Latest_V1_14 {
     ) -> Result<bool> {
         make_enum_progress! {
             enum VectorStore {
-                UpdateInternalVersions,
+                UpgradeEmbedders,
             }
         };
 
-        progress.update_progress(VectorStore::UpdateInternalVersions);
+        progress.update_progress(VectorStore::UpgradeEmbedders);
 
+        let embedders = index.embedding_configs(wtxn)?;
+        let total_embedders = embedders.len() as u32;
+        let mut any_changes = false;
+
+        // taken once: every embedder's vectors live under their own `embedder_id` prefix, so
+        // this snapshot of the not-yet-migrated data stays valid for every iteration below.
         let rtxn = index.read_txn()?;
-        arroy::upgrade::cosine_from_0_5_to_0_6(
-            &rtxn,
-            index.vector_arroy,
-            &mut wtxn,
-            index.vector_arroy,
-        )?;
-
-        Ok(true)
+
+        for (i, config) in embedders.into_iter().enumerate() {
+            progress.update_progress(VariableNameStep::<Embedder>::new(
+                config.name.clone(),
+                i as u32,
+                total_embedders,
+            ));
+
+            // this embedder may already have been migrated earlier in this same attempt, e.g.
+            // if this upgrade step is retried before the chain's final commit (see
+            // `checkpoint_key` for why this does not cover a process crash)
+            if Self::is_upgraded(wtxn, index, &config.name)? {
+                continue;
+            }
+
+            let embedder_id = index.embedder_category_id(&rtxn, &config.name)?;
+            let distance = config.config.embedder_options.distance();
+            let changed = Self::upgrade_embedder(&rtxn, wtxn, index, embedder_id, distance)?;
+            any_changes |= changed;
+
+            Self::mark_upgraded(wtxn, index, &config.name)?;
+        }
+
+        Ok(any_changes)
     }
 
     fn target_version(&self) -> (u32, u32, u32) {